@@ -2,27 +2,209 @@
 /// ([the RISC-V Instruction Set Manual](https://riscv.org/specifications/),
 ///  Volume 1, Version, 2.1, Section 2.4).
 
+use std::io::{self, Write};
+use std::ops::{Add, BitAnd, BitOr, BitXor, Sub};
+
 type Register = usize;
 
-struct Processor {
-    // XXX make registers just 4 bytes that are interpreted as necessary,
-    //     e.g. SLTIU wants things treated as unsigned.
-    registers: [u32; 33], // registers[0] is unused; hard-wired to 0.
+/// The active integer register width (XLEN).
+///
+/// Implemented for `u32` (RV32I) and `u64` (RV64I). Everything that keys
+/// off register width -- immediate sign-extension, shift-amount masking,
+/// signed comparisons -- goes through this trait so `Processor` can share
+/// one implementation across both widths.
+trait Xlen:
+    Copy
+    + Default
+    + PartialEq
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + BitXor<Output = Self>
+{
+    /// Mask applied to a shift amount: `0x1f` for RV32I, `0x3f` for RV64I.
+    const SHAMT_MASK: u32;
+
+    /// Sign-extend a 32-bit immediate (or computed 32-bit result) to XLEN.
+    fn from_i32(v: i32) -> Self;
+    /// Truncate to the low 32 bits, e.g. to compute a (32-bit) memory
+    /// address or feed a word-sized ALU helper.
+    fn low32(self) -> u32;
+    fn wrapping_add(self, rhs: Self) -> Self;
+    fn wrapping_sub(self, rhs: Self) -> Self;
+    fn signed_lt(self, rhs: Self) -> bool;
+    fn shift_left(self, amt: u32) -> Self;
+    fn shift_right_logical(self, amt: u32) -> Self;
+    fn shift_right_arithmetic(self, amt: u32) -> Self;
+}
+
+impl Xlen for u32 {
+    const SHAMT_MASK: u32 = 0x1f;
+
+    fn from_i32(v: i32) -> Self {
+        v as u32
+    }
+
+    fn low32(self) -> u32 {
+        self
+    }
+
+    fn wrapping_add(self, rhs: Self) -> Self {
+        u32::wrapping_add(self, rhs)
+    }
+
+    fn wrapping_sub(self, rhs: Self) -> Self {
+        u32::wrapping_sub(self, rhs)
+    }
+
+    fn signed_lt(self, rhs: Self) -> bool {
+        (self as i32) < (rhs as i32)
+    }
+
+    fn shift_left(self, amt: u32) -> Self {
+        self << amt
+    }
+
+    fn shift_right_logical(self, amt: u32) -> Self {
+        self >> amt
+    }
+
+    fn shift_right_arithmetic(self, amt: u32) -> Self {
+        ((self as i32) >> amt) as u32
+    }
 }
 
-impl Processor {
-    fn new() -> Processor {
-        Processor { registers: [0; 33] }
+impl Xlen for u64 {
+    const SHAMT_MASK: u32 = 0x3f;
+
+    fn from_i32(v: i32) -> Self {
+        v as i64 as u64
+    }
+
+    fn low32(self) -> u32 {
+        self as u32
+    }
+
+    fn wrapping_add(self, rhs: Self) -> Self {
+        u64::wrapping_add(self, rhs)
+    }
+
+    fn wrapping_sub(self, rhs: Self) -> Self {
+        u64::wrapping_sub(self, rhs)
+    }
+
+    fn signed_lt(self, rhs: Self) -> bool {
+        (self as i64) < (rhs as i64)
+    }
+
+    fn shift_left(self, amt: u32) -> Self {
+        self << amt
+    }
+
+    fn shift_right_logical(self, amt: u32) -> Self {
+        self >> amt
     }
 
-    fn get(&mut self, reg: Register) -> u32 {
+    fn shift_right_arithmetic(self, amt: u32) -> Self {
+        ((self as i64) >> amt) as u64
+    }
+}
+
+/// The size, in bytes, of a `Processor`'s main memory.
+const MEMORY_SIZE: usize = 1 << 20; // 1 MiB.
+
+/// A flat, byte-addressed main memory.
+struct Memory {
+    bytes: Vec<u8>,
+}
+
+impl Memory {
+    fn new(size: usize) -> Memory {
+        Memory {
+            bytes: vec![0; size],
+        }
+    }
+
+    fn read_u8(&self, addr: u32) -> Result<u8, Trap> {
+        self.bytes
+            .get(addr as usize)
+            .copied()
+            .ok_or(Trap::LoadAccessFault(addr))
+    }
+
+    fn read_u16(&self, addr: u32) -> Result<u16, Trap> {
+        if addr % 2 != 0 {
+            return Err(Trap::LoadAddressMisaligned(addr));
+        }
+        let lo = self.read_u8(addr)?;
+        let hi = self.read_u8(addr + 1)?;
+        Ok(u16::from_le_bytes([lo, hi]))
+    }
+
+    fn read_u32(&self, addr: u32) -> Result<u32, Trap> {
+        if addr % 4 != 0 {
+            return Err(Trap::LoadAddressMisaligned(addr));
+        }
+        let b0 = self.read_u8(addr)?;
+        let b1 = self.read_u8(addr + 1)?;
+        let b2 = self.read_u8(addr + 2)?;
+        let b3 = self.read_u8(addr + 3)?;
+        Ok(u32::from_le_bytes([b0, b1, b2, b3]))
+    }
+
+    fn write_u8(&mut self, addr: u32, val: u8) -> Result<(), Trap> {
+        let byte = self
+            .bytes
+            .get_mut(addr as usize)
+            .ok_or(Trap::StoreAccessFault(addr))?;
+        *byte = val;
+        Ok(())
+    }
+
+    fn write_u16(&mut self, addr: u32, val: u16) -> Result<(), Trap> {
+        if addr % 2 != 0 {
+            return Err(Trap::StoreAddressMisaligned(addr));
+        }
+        let [lo, hi] = val.to_le_bytes();
+        self.write_u8(addr, lo)?;
+        self.write_u8(addr + 1, hi)
+    }
+
+    fn write_u32(&mut self, addr: u32, val: u32) -> Result<(), Trap> {
+        if addr % 4 != 0 {
+            return Err(Trap::StoreAddressMisaligned(addr));
+        }
+        let [b0, b1, b2, b3] = val.to_le_bytes();
+        self.write_u8(addr, b0)?;
+        self.write_u8(addr + 1, b1)?;
+        self.write_u8(addr + 2, b2)?;
+        self.write_u8(addr + 3, b3)
+    }
+}
+
+struct Processor<X: Xlen = u32> {
+    registers: [X; 33], // registers[0] is unused; hard-wired to 0.
+    memory: Memory,
+}
+
+impl<X: Xlen> Processor<X> {
+    fn new() -> Processor<X> {
+        Processor {
+            registers: [X::default(); 33],
+            memory: Memory::new(MEMORY_SIZE),
+        }
+    }
+
+    fn get(&mut self, reg: Register) -> X {
         match reg {
-            0 => 0,
+            0 => X::default(),
             _ => self.registers[reg],
         }
     }
 
-    fn set(&mut self, reg: Register, val: u32) {
+    fn set(&mut self, reg: Register, val: X) {
         match reg {
             0 => (),  // No-op
             _ => self.registers[reg] = val,
@@ -34,70 +216,364 @@ impl Processor {
     /// Overflow is ignored.
     /// `ADDI rd, rs1, 0` == `MV rd, rs1`
     fn addi(&mut self, rd: Register, rs1: Register, imm: u32) {
-        let signed_imm = imm as i32;
-        let rs1_val = self.get(rs1) as i32;
-        let (result, _) = rs1_val.overflowing_add(signed_imm);
-        self.set(rd, result as u32);
+        let signed_imm = X::from_i32(imm as i32);
+        let rs1_val = self.get(rs1);
+        self.set(rd, rs1_val.wrapping_add(signed_imm));
     }
 
     /// Check if `rs1` is less than the sign-extended `imm`.
     fn slti(&mut self, rd: Register, rs1: Register, imm: u32) {
-        let signed_imm = imm as i32;
-        let rs1_val = self.get(rs1) as i32;
-        self.set(rd, if rs1_val < signed_imm { 1 } else { 0 })
+        let signed_imm = X::from_i32(imm as i32);
+        let rs1_val = self.get(rs1);
+        self.set(
+            rd,
+            if rs1_val.signed_lt(signed_imm) {
+                X::from_i32(1)
+            } else {
+                X::from_i32(0)
+            },
+        )
     }
 
     /// Check if `rs1` is less than sign-extended `imm` in an unsigned comparison.
     ///
     /// `SLTIU rd, rs1, 1` == `SEQZ rd, rs`
     fn sltiu(&mut self, rd: Register, rs1: Register, imm: u32) {
-        let rs1_val: u32 = self.get(rs1);
+        let rs1_val = self.get(rs1);
         if imm == 1 {
             // SEQZ pseudo-op.
-            self.set(rd, if rs1_val == 0 { 1 } else { 0 })
+            self.set(
+                rd,
+                if rs1_val == X::default() {
+                    X::from_i32(1)
+                } else {
+                    X::from_i32(0)
+                },
+            )
         } else {
-            self.set(rd, if rs1_val < imm { 1 } else { 0 })
+            let imm_val = X::from_i32(imm as i32);
+            self.set(
+                rd,
+                if rs1_val < imm_val {
+                    X::from_i32(1)
+                } else {
+                    X::from_i32(0)
+                },
+            )
         }
     }
 
-    /// Perform a bitwise AND against `imm`.
+    /// Perform a bitwise AND against the sign-extended `imm`.
     fn andi(&mut self, rd: Register, rs1: Register, imm: u32) {
         let rs1_val = self.get(rs1);
-        self.set(rd, rs1_val & imm);
+        self.set(rd, rs1_val & X::from_i32(imm as i32));
     }
 
-    /// Perform a bitwise OR against `imm`.
+    /// Perform a bitwise OR against the sign-extended `imm`.
     fn ori(&mut self, rd: Register, rs1: Register, imm: u32) {
         let rs1_val = self.get(rs1);
-        self.set(rd, rs1_val | imm);
+        self.set(rd, rs1_val | X::from_i32(imm as i32));
     }
 
-    /// Perform a bitwise XOR against `imm`.
+    /// Perform a bitwise XOR against the sign-extended `imm`.
     ///
     /// `XORI rd, sr1, -1` == `NOT rd, rs`
     fn xori(&mut self, rd: Register, rs1: Register, imm: u32) {
         let rs1_val = self.get(rs1);
-        self.set(rd, rs1_val ^ imm);
+        self.set(rd, rs1_val ^ X::from_i32(imm as i32));
     }
 
     /// Perform a logical left shift to `rs1`.
+    ///
+    /// Only the bits of `imm` selected by `X::SHAMT_MASK` (`shamt`) are
+    /// used: the low 5 bits for RV32I, the low 6 bits for RV64I.
     fn slli(&mut self, rd: Register, rs1: Register, imm: u32) {
         let rs1_val = self.get(rs1);
-        self.set(rd, rs1_val << imm)
+        let shamt = imm & X::SHAMT_MASK;
+        self.set(rd, rs1_val.shift_left(shamt))
     }
 
     /// Perform a logical right shift to `rs1`.
     /// This means zeroes are shifted into the upper bits.
+    ///
+    /// Only the bits of `imm` selected by `X::SHAMT_MASK` (`shamt`) are
+    /// used: the low 5 bits for RV32I, the low 6 bits for RV64I.
     fn srli(&mut self, rd: Register, rs1: Register, imm: u32) {
         let rs1_val = self.get(rs1);
-        self.set(rd, rs1_val >> imm)
+        let shamt = imm & X::SHAMT_MASK;
+        self.set(rd, rs1_val.shift_right_logical(shamt))
     }
 
     /// Perform an arithmetic right shift to `rs1`.
     /// This means the original sign bit is shifted into the upper bits.
+    ///
+    /// Only the bits of `imm` selected by `X::SHAMT_MASK` (`shamt`) are
+    /// used: the low 5 bits for RV32I, the low 6 bits for RV64I.
     fn srai(&mut self, rd: Register, rs1: Register, imm: u32) {
-        let rs1_val = self.get(rs1) as i32;
-        self.set(rd, (rs1_val >> imm) as u32)
+        let rs1_val = self.get(rs1);
+        let shamt = imm & X::SHAMT_MASK;
+        self.set(rd, rs1_val.shift_right_arithmetic(shamt))
+    }
+
+    /// Multiply `rs1` by `rs2`, keeping the low 32 bits of the product.
+    ///
+    /// Operates on (and sign-extends back from) the low 32 bits of each
+    /// XLEN-wide register, matching RV64's `MULW`-family convention.
+    fn mul(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let rs1_val = self.get(rs1).low32();
+        let rs2_val = self.get(rs2).low32();
+        self.set(rd, X::from_i32(rs1_val.wrapping_mul(rs2_val) as i32));
+    }
+
+    /// Multiply `rs1` by `rs2` as signed values, keeping the high 32 bits
+    /// of the 64-bit product.
+    fn mulh(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let rs1_val = self.get(rs1).low32() as i32 as i64;
+        let rs2_val = self.get(rs2).low32() as i32 as i64;
+        let product = rs1_val * rs2_val;
+        self.set(rd, X::from_i32((product >> 32) as i32));
+    }
+
+    /// Multiply `rs1` by `rs2` as unsigned values, keeping the high 32 bits
+    /// of the 64-bit product.
+    fn mulhu(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let rs1_val = self.get(rs1).low32() as u64;
+        let rs2_val = self.get(rs2).low32() as u64;
+        let product = rs1_val * rs2_val;
+        self.set(rd, X::from_i32((product >> 32) as i32));
+    }
+
+    /// Multiply signed `rs1` by unsigned `rs2`, keeping the high 32 bits of
+    /// the 64-bit product.
+    fn mulhsu(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let rs1_val = self.get(rs1).low32() as i32 as i64;
+        let rs2_val = self.get(rs2).low32() as u64 as i64;
+        let product = rs1_val * rs2_val;
+        self.set(rd, X::from_i32((product >> 32) as i32));
+    }
+
+    /// Signed division of `rs1` by `rs2`.
+    ///
+    /// Division by zero yields `-1` and the `i32::MIN / -1` overflow case
+    /// yields `i32::MIN`, per the RISC-V M extension (neither traps).
+    fn div(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let rs1_val = self.get(rs1).low32() as i32;
+        let rs2_val = self.get(rs2).low32() as i32;
+        let result = if rs2_val == 0 {
+            -1
+        } else if rs1_val == i32::MIN && rs2_val == -1 {
+            i32::MIN
+        } else {
+            rs1_val.wrapping_div(rs2_val)
+        };
+        self.set(rd, X::from_i32(result));
+    }
+
+    /// Unsigned division of `rs1` by `rs2`.
+    ///
+    /// Division by zero yields `0xFFFF_FFFF` rather than trapping.
+    fn divu(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let rs1_val = self.get(rs1).low32();
+        let rs2_val = self.get(rs2).low32();
+        let result = if rs2_val == 0 {
+            0xFFFF_FFFF
+        } else {
+            rs1_val / rs2_val
+        };
+        self.set(rd, X::from_i32(result as i32));
+    }
+
+    /// Signed remainder of `rs1` divided by `rs2`.
+    ///
+    /// Division by zero yields the dividend unchanged and the
+    /// `i32::MIN / -1` overflow case yields `0`, per the RISC-V M
+    /// extension (neither traps).
+    fn rem(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let rs1_val = self.get(rs1).low32() as i32;
+        let rs2_val = self.get(rs2).low32() as i32;
+        let result = if rs2_val == 0 {
+            rs1_val
+        } else if rs1_val == i32::MIN && rs2_val == -1 {
+            0
+        } else {
+            rs1_val.wrapping_rem(rs2_val)
+        };
+        self.set(rd, X::from_i32(result));
+    }
+
+    /// Unsigned remainder of `rs1` divided by `rs2`.
+    ///
+    /// Division by zero yields the dividend unchanged rather than trapping.
+    fn remu(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let rs1_val = self.get(rs1).low32();
+        let rs2_val = self.get(rs2).low32();
+        let result = if rs2_val == 0 { rs1_val } else { rs1_val % rs2_val };
+        self.set(rd, X::from_i32(result as i32));
+    }
+
+    /// Add `rs1` and `rs2`.
+    ///
+    /// Overflow is ignored.
+    fn add(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let rs1_val = self.get(rs1);
+        let rs2_val = self.get(rs2);
+        self.set(rd, rs1_val.wrapping_add(rs2_val));
+    }
+
+    /// Subtract `rs2` from `rs1`.
+    ///
+    /// Overflow is ignored.
+    fn sub(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let rs1_val = self.get(rs1);
+        let rs2_val = self.get(rs2);
+        self.set(rd, rs1_val.wrapping_sub(rs2_val));
+    }
+
+    /// Perform a logical left shift to `rs1` by the shift amount in `rs2`.
+    ///
+    /// Only the bits of `rs2` selected by `X::SHAMT_MASK` (`shamt`) are used.
+    fn sll(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let rs1_val = self.get(rs1);
+        let shamt = self.get(rs2).low32() & X::SHAMT_MASK;
+        self.set(rd, rs1_val.shift_left(shamt));
+    }
+
+    /// Perform a logical right shift to `rs1` by the shift amount in `rs2`.
+    ///
+    /// Only the bits of `rs2` selected by `X::SHAMT_MASK` (`shamt`) are used.
+    fn srl(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let rs1_val = self.get(rs1);
+        let shamt = self.get(rs2).low32() & X::SHAMT_MASK;
+        self.set(rd, rs1_val.shift_right_logical(shamt));
+    }
+
+    /// Perform an arithmetic right shift to `rs1` by the shift amount in
+    /// `rs2`.
+    ///
+    /// Only the bits of `rs2` selected by `X::SHAMT_MASK` (`shamt`) are used.
+    fn sra(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let rs1_val = self.get(rs1);
+        let shamt = self.get(rs2).low32() & X::SHAMT_MASK;
+        self.set(rd, rs1_val.shift_right_arithmetic(shamt));
+    }
+
+    /// Check if `rs1` is less than `rs2` in a signed comparison.
+    fn slt(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let rs1_val = self.get(rs1);
+        let rs2_val = self.get(rs2);
+        self.set(
+            rd,
+            if rs1_val.signed_lt(rs2_val) {
+                X::from_i32(1)
+            } else {
+                X::from_i32(0)
+            },
+        )
+    }
+
+    /// Check if `rs1` is less than `rs2` in an unsigned comparison.
+    ///
+    /// `SLTU rd, x0, rs2` == `SNEZ rd, rs2`
+    fn sltu(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let rs1_val = self.get(rs1);
+        let rs2_val = self.get(rs2);
+        self.set(
+            rd,
+            if rs1_val < rs2_val {
+                X::from_i32(1)
+            } else {
+                X::from_i32(0)
+            },
+        )
+    }
+
+    /// Perform a bitwise AND between `rs1` and `rs2`.
+    fn and(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let rs1_val = self.get(rs1);
+        let rs2_val = self.get(rs2);
+        self.set(rd, rs1_val & rs2_val);
+    }
+
+    /// Perform a bitwise OR between `rs1` and `rs2`.
+    fn or(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let rs1_val = self.get(rs1);
+        let rs2_val = self.get(rs2);
+        self.set(rd, rs1_val | rs2_val);
+    }
+
+    /// Perform a bitwise XOR between `rs1` and `rs2`.
+    fn xor(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let rs1_val = self.get(rs1);
+        let rs2_val = self.get(rs2);
+        self.set(rd, rs1_val ^ rs2_val);
+    }
+
+    /// Effective address of a load/store: `rs1 + sign_extend(imm)`.
+    ///
+    /// Addresses stay 32 bits wide regardless of XLEN.
+    fn effective_addr(&mut self, rs1: Register, imm: u32) -> u32 {
+        self.get(rs1).low32().wrapping_add(imm)
+    }
+
+    /// Load a sign-extended byte from memory.
+    fn lb(&mut self, rd: Register, rs1: Register, imm: u32) -> Result<(), Trap> {
+        let addr = self.effective_addr(rs1, imm);
+        let val = self.memory.read_u8(addr)?;
+        self.set(rd, X::from_i32(val as i8 as i32));
+        Ok(())
+    }
+
+    /// Load a sign-extended halfword from memory.
+    fn lh(&mut self, rd: Register, rs1: Register, imm: u32) -> Result<(), Trap> {
+        let addr = self.effective_addr(rs1, imm);
+        let val = self.memory.read_u16(addr)?;
+        self.set(rd, X::from_i32(val as i16 as i32));
+        Ok(())
+    }
+
+    /// Load a word from memory.
+    fn lw(&mut self, rd: Register, rs1: Register, imm: u32) -> Result<(), Trap> {
+        let addr = self.effective_addr(rs1, imm);
+        let val = self.memory.read_u32(addr)?;
+        self.set(rd, X::from_i32(val as i32));
+        Ok(())
+    }
+
+    /// Load a zero-extended byte from memory.
+    fn lbu(&mut self, rd: Register, rs1: Register, imm: u32) -> Result<(), Trap> {
+        let addr = self.effective_addr(rs1, imm);
+        let val = self.memory.read_u8(addr)?;
+        self.set(rd, X::from_i32(val as i32));
+        Ok(())
+    }
+
+    /// Load a zero-extended halfword from memory.
+    fn lhu(&mut self, rd: Register, rs1: Register, imm: u32) -> Result<(), Trap> {
+        let addr = self.effective_addr(rs1, imm);
+        let val = self.memory.read_u16(addr)?;
+        self.set(rd, X::from_i32(val as i32));
+        Ok(())
+    }
+
+    /// Store the low 8 bits of `rs2` to memory.
+    fn sb(&mut self, rs1: Register, rs2: Register, imm: u32) -> Result<(), Trap> {
+        let addr = self.effective_addr(rs1, imm);
+        let val = self.get(rs2).low32() as u8;
+        self.memory.write_u8(addr, val)
+    }
+
+    /// Store the low 16 bits of `rs2` to memory.
+    fn sh(&mut self, rs1: Register, rs2: Register, imm: u32) -> Result<(), Trap> {
+        let addr = self.effective_addr(rs1, imm);
+        let val = self.get(rs2).low32() as u16;
+        self.memory.write_u16(addr, val)
+    }
+
+    /// Store all 32 bits of `rs2` to memory.
+    fn sw(&mut self, rs1: Register, rs2: Register, imm: u32) -> Result<(), Trap> {
+        let addr = self.effective_addr(rs1, imm);
+        let val = self.get(rs2).low32();
+        self.memory.write_u32(addr, val)
     }
 }
 
@@ -108,9 +584,268 @@ fn sign_extend(imm: u32) -> u32 {
     extended_imm as u32
 }
 
+/// Sign-extend `value`, treating bit `bits - 1` as the sign bit.
+fn sign_extend_bits(value: u32, bits: u32) -> u32 {
+    let shift = 32 - bits;
+    (((value << shift) as i32) >> shift) as u32
+}
+
+const OPCODE_OP_IMM: u32 = 0b001_0011;
+const OPCODE_LOAD: u32 = 0b000_0011;
+const OPCODE_STORE: u32 = 0b010_0011;
+const OPCODE_OP: u32 = 0b011_0011;
+const OPCODE_SYSTEM: u32 = 0b111_0011;
+
+/// `a7`/`x17`, the register holding the syscall number on `ecall`.
+const REG_A7: Register = 17;
+/// `a0`/`x10`, the first syscall argument.
+const REG_A0: Register = 10;
+/// `a1`/`x11`, the second syscall argument.
+const REG_A1: Register = 11;
+/// `a2`/`x12`, the third syscall argument.
+const REG_A2: Register = 12;
+
+/// Halt the machine; the exit status comes from `a0`.
+const SYS_EXIT: u32 = 1;
+/// Write `a2` bytes from the memory buffer pointed to by `a1` to stdout.
+const SYS_WRITE: u32 = 2;
+/// Halt the machine immediately, as if powered off.
+const SYS_SHUTDOWN: u32 = 3;
+
+/// What happened after executing one instruction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExecOutcome {
+    /// Keep fetching; nothing to report.
+    Continue,
+    /// `EXIT`/`SHUTDOWN` fired; the machine should stop with this status.
+    Halt(i32),
+    /// `EBREAK` fired; the machine should stop for a debugger.
+    Breakpoint,
+}
+
+/// An exceptional condition raised while executing an instruction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Trap {
+    /// The instruction word does not correspond to any valid encoding.
+    /// Carries the offending word.
+    IllegalInstruction(u32),
+    /// A load address was not aligned to its access size. Carries the
+    /// offending address.
+    LoadAddressMisaligned(u32),
+    /// A load address was outside the bounds of memory. Carries the
+    /// offending address.
+    LoadAccessFault(u32),
+    /// A store address was not aligned to its access size. Carries the
+    /// offending address.
+    StoreAddressMisaligned(u32),
+    /// A store address was outside the bounds of memory. Carries the
+    /// offending address.
+    StoreAccessFault(u32),
+}
+
+/// A 32-bit RISC-V instruction, split into its constituent fields.
+///
+/// All five immediate encodings (I/S/B/U/J) are reconstructed eagerly;
+/// `execute` picks whichever one applies to `opcode`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Instruction {
+    word: u32,
+    opcode: u32,
+    rd: Register,
+    funct3: u32,
+    rs1: Register,
+    rs2: Register,
+    funct7: u32,
+    imm_i: u32,
+    imm_s: u32,
+    imm_b: u32,
+    imm_u: u32,
+    imm_j: u32,
+}
+
+/// Split a raw 32-bit instruction word into its fields.
+fn decode(word: u32) -> Instruction {
+    let opcode = word & 0x7f;
+    let rd = ((word >> 7) & 0x1f) as Register;
+    let funct3 = (word >> 12) & 0x7;
+    let rs1 = ((word >> 15) & 0x1f) as Register;
+    let rs2 = ((word >> 20) & 0x1f) as Register;
+    let funct7 = (word >> 25) & 0x7f;
+
+    let imm_i = sign_extend_bits(word >> 20, 12);
+    let imm_s = sign_extend_bits(((word >> 25) << 5) | ((word >> 7) & 0x1f), 12);
+    let imm_b = sign_extend_bits(
+        (((word >> 31) & 0x1) << 12)
+            | (((word >> 7) & 0x1) << 11)
+            | (((word >> 25) & 0x3f) << 5)
+            | (((word >> 8) & 0xf) << 1),
+        13,
+    );
+    let imm_u = word & 0xffff_f000;
+    let imm_j = sign_extend_bits(
+        (((word >> 31) & 0x1) << 20)
+            | (((word >> 12) & 0xff) << 12)
+            | (((word >> 20) & 0x1) << 11)
+            | (((word >> 21) & 0x3ff) << 1),
+        21,
+    );
+
+    Instruction {
+        word,
+        opcode,
+        rd,
+        funct3,
+        rs1,
+        rs2,
+        funct7,
+        imm_i,
+        imm_s,
+        imm_b,
+        imm_u,
+        imm_j,
+    }
+}
+
+impl<X: Xlen> Processor<X> {
+    /// Execute a single decoded instruction.
+    fn execute(&mut self, inst: Instruction) -> Result<ExecOutcome, Trap> {
+        match inst.opcode {
+            OPCODE_OP_IMM => self.execute_op_imm(inst),
+            OPCODE_OP => self.execute_op(inst),
+            OPCODE_LOAD => self.execute_load(inst),
+            OPCODE_STORE => self.execute_store(inst),
+            OPCODE_SYSTEM => self.execute_system(inst),
+            _ => Err(Trap::IllegalInstruction(inst.word)),
+        }
+    }
+
+    /// Dispatch a LOAD (I-type) instruction by `funct3`.
+    fn execute_load(&mut self, inst: Instruction) -> Result<ExecOutcome, Trap> {
+        match inst.funct3 {
+            0b000 => self.lb(inst.rd, inst.rs1, inst.imm_i),
+            0b001 => self.lh(inst.rd, inst.rs1, inst.imm_i),
+            0b010 => self.lw(inst.rd, inst.rs1, inst.imm_i),
+            0b100 => self.lbu(inst.rd, inst.rs1, inst.imm_i),
+            0b101 => self.lhu(inst.rd, inst.rs1, inst.imm_i),
+            _ => return Err(Trap::IllegalInstruction(inst.word)),
+        }?;
+        Ok(ExecOutcome::Continue)
+    }
+
+    /// Dispatch a STORE (S-type) instruction by `funct3`.
+    fn execute_store(&mut self, inst: Instruction) -> Result<ExecOutcome, Trap> {
+        match inst.funct3 {
+            0b000 => self.sb(inst.rs1, inst.rs2, inst.imm_s),
+            0b001 => self.sh(inst.rs1, inst.rs2, inst.imm_s),
+            0b010 => self.sw(inst.rs1, inst.rs2, inst.imm_s),
+            _ => return Err(Trap::IllegalInstruction(inst.word)),
+        }?;
+        Ok(ExecOutcome::Continue)
+    }
+
+    /// Dispatch an OP-IMM (I-type arithmetic) instruction by `funct3`.
+    fn execute_op_imm(&mut self, inst: Instruction) -> Result<ExecOutcome, Trap> {
+        match inst.funct3 {
+            0b000 => self.addi(inst.rd, inst.rs1, inst.imm_i),
+            0b010 => self.slti(inst.rd, inst.rs1, inst.imm_i),
+            0b011 => self.sltiu(inst.rd, inst.rs1, inst.imm_i),
+            0b100 => self.xori(inst.rd, inst.rs1, inst.imm_i),
+            0b110 => self.ori(inst.rd, inst.rs1, inst.imm_i),
+            0b111 => self.andi(inst.rd, inst.rs1, inst.imm_i),
+            0b001 => {
+                if inst.funct7 != 0b000_0000 {
+                    return Err(Trap::IllegalInstruction(inst.word));
+                }
+                self.slli(inst.rd, inst.rs1, inst.imm_i)
+            }
+            0b101 => match inst.funct7 {
+                0b000_0000 => self.srli(inst.rd, inst.rs1, inst.imm_i),
+                0b010_0000 => self.srai(inst.rd, inst.rs1, inst.imm_i),
+                _ => return Err(Trap::IllegalInstruction(inst.word)),
+            },
+            _ => unreachable!("funct3 is only 3 bits wide"),
+        }
+        Ok(ExecOutcome::Continue)
+    }
+
+    /// Dispatch an OP (R-type arithmetic) instruction by `funct3`/`funct7`.
+    fn execute_op(&mut self, inst: Instruction) -> Result<ExecOutcome, Trap> {
+        match (inst.funct3, inst.funct7) {
+            (0b000, 0b000_0000) => self.add(inst.rd, inst.rs1, inst.rs2),
+            (0b000, 0b010_0000) => self.sub(inst.rd, inst.rs1, inst.rs2),
+            (0b000, 0b000_0001) => self.mul(inst.rd, inst.rs1, inst.rs2),
+            (0b001, 0b000_0000) => self.sll(inst.rd, inst.rs1, inst.rs2),
+            (0b001, 0b000_0001) => self.mulh(inst.rd, inst.rs1, inst.rs2),
+            (0b010, 0b000_0000) => self.slt(inst.rd, inst.rs1, inst.rs2),
+            (0b010, 0b000_0001) => self.mulhsu(inst.rd, inst.rs1, inst.rs2),
+            (0b011, 0b000_0000) => self.sltu(inst.rd, inst.rs1, inst.rs2),
+            (0b011, 0b000_0001) => self.mulhu(inst.rd, inst.rs1, inst.rs2),
+            (0b100, 0b000_0000) => self.xor(inst.rd, inst.rs1, inst.rs2),
+            (0b100, 0b000_0001) => self.div(inst.rd, inst.rs1, inst.rs2),
+            (0b101, 0b000_0000) => self.srl(inst.rd, inst.rs1, inst.rs2),
+            (0b101, 0b010_0000) => self.sra(inst.rd, inst.rs1, inst.rs2),
+            (0b101, 0b000_0001) => self.divu(inst.rd, inst.rs1, inst.rs2),
+            (0b110, 0b000_0000) => self.or(inst.rd, inst.rs1, inst.rs2),
+            (0b110, 0b000_0001) => self.rem(inst.rd, inst.rs1, inst.rs2),
+            (0b111, 0b000_0000) => self.and(inst.rd, inst.rs1, inst.rs2),
+            (0b111, 0b000_0001) => self.remu(inst.rd, inst.rs1, inst.rs2),
+            _ => return Err(Trap::IllegalInstruction(inst.word)),
+        }
+        Ok(ExecOutcome::Continue)
+    }
+
+    /// Dispatch a SYSTEM instruction: `ECALL` (`imm_i == 0`) or `EBREAK`
+    /// (`imm_i == 1`).
+    fn execute_system(&mut self, inst: Instruction) -> Result<ExecOutcome, Trap> {
+        if inst.funct3 != 0b000 {
+            return Err(Trap::IllegalInstruction(inst.word));
+        }
+        match inst.imm_i {
+            0 => self.ecall(),
+            1 => Ok(ExecOutcome::Breakpoint),
+            _ => Err(Trap::IllegalInstruction(inst.word)),
+        }
+    }
+
+    /// Dispatch a syscall on the value in `a7`, with arguments in `a0`-`a2`.
+    ///
+    /// No syscall writes a result back to `a0`; an unknown syscall number
+    /// is ignored.
+    fn ecall(&mut self) -> Result<ExecOutcome, Trap> {
+        match self.get(REG_A7).low32() {
+            SYS_EXIT => Ok(ExecOutcome::Halt(self.get(REG_A0).low32() as i32)),
+            SYS_WRITE => {
+                let addr = self.get(REG_A1).low32();
+                let len = self.get(REG_A2).low32();
+                let mut buf = Vec::with_capacity(len as usize);
+                for offset in 0..len {
+                    buf.push(self.memory.read_u8(addr.wrapping_add(offset))?);
+                }
+                let _ = io::stdout().write_all(&buf);
+                Ok(ExecOutcome::Continue)
+            }
+            SYS_SHUTDOWN => Ok(ExecOutcome::Halt(0)),
+            _ => Ok(ExecOutcome::Continue),
+        }
+    }
+
+    /// Run a program of raw instruction words from the start until a
+    /// halting syscall or `EBREAK` stops it, or a trap is raised.
+    fn run(&mut self, program: &[u32]) -> Result<ExecOutcome, Trap> {
+        let mut pc = 0usize;
+        while pc < program.len() {
+            match self.execute(decode(program[pc]))? {
+                ExecOutcome::Continue => pc += 1,
+                outcome => return Ok(outcome),
+            }
+        }
+        Ok(ExecOutcome::Continue)
+    }
+}
+
 macro_rules! test_imm_op {
     ($test_num: expr, $inst:ident, $result:expr, $val1:expr, $imm:expr) => {{
-        let mut cpu = Processor::new();
+        let mut cpu = Processor::<u32>::new();
         let rd: Register = 1;
         let rs1: Register = 3;
         cpu.set(rs1, $val1);
@@ -121,7 +856,7 @@ macro_rules! test_imm_op {
 
 macro_rules! test_imm_src1_eq_dest {
     ($test_num:expr, $inst:ident, $result:expr, $val1:expr, $imm:expr) => {{
-        let mut cpu = Processor::new();
+        let mut cpu = Processor::<u32>::new();
         let rd: Register = 1;
         let rs1: Register = 1;
         cpu.set(rs1, $val1);
@@ -132,7 +867,7 @@ macro_rules! test_imm_src1_eq_dest {
 
 macro_rules! test_imm_zerosrc1 {
     ($test_num:expr, $inst:ident, $result:expr, $imm:expr) => {{
-        let mut cpu = Processor::new();
+        let mut cpu = Processor::<u32>::new();
         let rd: Register = 1;
         let rs1: Register = 0;
         cpu.$inst(rd, rs1, sign_extend($imm));
@@ -142,7 +877,7 @@ macro_rules! test_imm_zerosrc1 {
 
 macro_rules! test_imm_zerodest {
     ($test_num:expr, $inst:ident, $val1:expr, $imm:expr) => {{
-        let mut cpu = Processor::new();
+        let mut cpu = Processor::<u32>::new();
         let rd: Register = 0;
         let rs1: Register = 1;
         cpu.$inst(rd, rs1, $imm);
@@ -150,12 +885,47 @@ macro_rules! test_imm_zerodest {
     }}
 }
 
+macro_rules! test_reg_reg_op {
+    ($test_num:expr, $inst:ident, $result:expr, $val1:expr, $val2:expr) => {{
+        let mut cpu = Processor::<u32>::new();
+        let rd: Register = 1;
+        let rs1: Register = 2;
+        let rs2: Register = 3;
+        cpu.set(rs1, $val1);
+        cpu.set(rs2, $val2);
+        cpu.$inst(rd, rs1, rs2);
+        assert_eq!($result, cpu.get(rd));
+    }};
+}
+
 macro_rules! test_srl {
 	($test_num:expr, $val1:expr, $imm:expr) => {{
 		test_imm_op!($test_num, srli, ($val1 as u32) >> $imm, $val1, $imm)
 	}};
 }
 
+#[test]
+fn decode_op_imm() {
+    // ADDI x1, x3, 7
+    let word = (7 << 20) | (3 << 15) | (0b000 << 12) | (1 << 7) | OPCODE_OP_IMM;
+    let inst = decode(word);
+    assert_eq!(OPCODE_OP_IMM, inst.opcode);
+    assert_eq!(1, inst.rd);
+    assert_eq!(0b000, inst.funct3);
+    assert_eq!(3, inst.rs1);
+    assert_eq!(7, inst.imm_i);
+}
+
+#[test]
+fn execute_addi() {
+    // ADDI x1, x3, 7
+    let word = (7 << 20) | (3 << 15) | (0b000 << 12) | (1 << 7) | OPCODE_OP_IMM;
+    let mut cpu = Processor::<u32>::new();
+    cpu.set(3, 35);
+    cpu.execute(decode(word)).unwrap();
+    assert_eq!(42, cpu.get(1));
+}
+
 #[test]
 fn addi() {
     // From https://github.com/riscv/riscv-tests/blob/master/isa/rv64ui/addi.S
@@ -371,3 +1141,256 @@ fn srai() {
     test_imm_zerosrc1!(24, srai, 0, 4);
     test_imm_zerodest!(25, srai, 33, 10);
 }
+
+#[test]
+fn shift_amount_is_masked_to_5_bits() {
+    // shamt=32 is indistinguishable from shamt=0 once masked.
+    test_imm_op!(1, slli, 0x00000001, 0x00000001, 32);
+    test_imm_op!(2, srli, 0x80000000, 0x80000000, 32);
+    test_imm_op!(3, srai, 0x80000000, 0x80000000, 32);
+}
+
+#[test]
+fn mul() {
+    test_reg_reg_op!(1, mul, 6, 2, 3);
+    test_reg_reg_op!(2, mul, 0xfffffffe, 0xffffffff, 2); // -1 * 2 == -2
+    test_reg_reg_op!(3, mul, 0, 0, 0xffffffff);
+}
+
+#[test]
+fn mulh() {
+    test_reg_reg_op!(1, mulh, 0, 2, 3);
+    test_reg_reg_op!(2, mulh, 0, 0xffffffff, 0xffffffff); // -1 * -1 == 1, high bits 0
+    test_reg_reg_op!(3, mulh, 0xffffffff, 0x80000000, 2); // i32::MIN * 2, high bits
+}
+
+#[test]
+fn mulhu() {
+    test_reg_reg_op!(1, mulhu, 0, 2, 3);
+    test_reg_reg_op!(2, mulhu, 0xfffffffe, 0xffffffff, 0xffffffff);
+}
+
+#[test]
+fn mulhsu() {
+    test_reg_reg_op!(1, mulhsu, 0, 2, 3);
+    test_reg_reg_op!(2, mulhsu, 0xffffffff, 0xffffffff, 1); // -1 * 1, high bits
+}
+
+#[test]
+fn div() {
+    test_reg_reg_op!(1, div, 2, 6, 3);
+    test_reg_reg_op!(2, div, 0xffffffff, 5, 0); // divide by zero
+    test_reg_reg_op!(3, div, 0x80000000, 0x80000000, 0xffffffff); // MIN / -1 overflow
+}
+
+#[test]
+fn divu() {
+    test_reg_reg_op!(1, divu, 2, 6, 3);
+    test_reg_reg_op!(2, divu, 0xffffffff, 5, 0); // divide by zero
+}
+
+#[test]
+fn rem() {
+    test_reg_reg_op!(1, rem, 0, 6, 3);
+    test_reg_reg_op!(2, rem, 5, 5, 0); // divide by zero
+    test_reg_reg_op!(3, rem, 0, 0x80000000, 0xffffffff); // MIN / -1 overflow
+}
+
+#[test]
+fn remu() {
+    test_reg_reg_op!(1, remu, 0, 6, 3);
+    test_reg_reg_op!(2, remu, 5, 5, 0); // divide by zero
+}
+
+#[test]
+fn add() {
+    test_reg_reg_op!(1, add, 5, 2, 3);
+    test_reg_reg_op!(2, add, 0, 0xffffffff, 1); // wraps around
+}
+
+#[test]
+fn sub() {
+    test_reg_reg_op!(1, sub, 0xffffffff, 2, 3); // wraps around
+    test_reg_reg_op!(2, sub, 1, 3, 2);
+}
+
+#[test]
+fn sll() {
+    test_reg_reg_op!(1, sll, 0x00000001, 0x00000001, 0);
+    test_reg_reg_op!(2, sll, 0x00000002, 0x00000001, 1);
+    test_reg_reg_op!(3, sll, 0x80000000, 0x00000001, 31);
+    // shamt=32 is indistinguishable from shamt=0 once masked.
+    test_reg_reg_op!(4, sll, 0x00000001, 0x00000001, 32);
+}
+
+#[test]
+fn srl() {
+    test_reg_reg_op!(1, srl, 0xffffffff, 0xffffffff, 0);
+    test_reg_reg_op!(2, srl, 0x7fffffff, 0xffffffff, 1);
+    test_reg_reg_op!(3, srl, 0x00000001, 0xffffffff, 31);
+    // shamt=32 is indistinguishable from shamt=0 once masked.
+    test_reg_reg_op!(4, srl, 0xffffffff, 0xffffffff, 32);
+}
+
+#[test]
+fn sra() {
+    test_reg_reg_op!(1, sra, 0xffffffff, 0x80000001, 31);
+    test_reg_reg_op!(2, sra, 0xc0000000, 0x80000000, 1);
+    test_reg_reg_op!(3, sra, 0x00000000, 0x7fffffff, 31);
+}
+
+#[test]
+fn slt() {
+    test_reg_reg_op!(1, slt, 0, 0x00000000, 0x00000000);
+    test_reg_reg_op!(2, slt, 1, 0xffffffff, 0x00000001); // -1 < 1
+    test_reg_reg_op!(3, slt, 0, 0x00000001, 0xffffffff); // 1 < -1 is false
+}
+
+#[test]
+fn sltu() {
+    test_reg_reg_op!(1, sltu, 0, 0x00000000, 0x00000000);
+    test_reg_reg_op!(2, sltu, 0, 0xffffffff, 0x00000001); // unsigned: not less
+    test_reg_reg_op!(3, sltu, 1, 0x00000001, 0xffffffff);
+
+    // SLTU rd, x0, rs2 == SNEZ rd, rs2
+    let mut cpu = Processor::<u32>::new();
+    cpu.set(2, 42);
+    cpu.sltu(1, 0, 2);
+    assert_eq!(1, cpu.get(1));
+}
+
+#[test]
+fn and() {
+    test_reg_reg_op!(1, and, 0x80000000, 0xffffffff, 0x80000000);
+    test_reg_reg_op!(2, and, 0, 0xffffffff, 0);
+}
+
+#[test]
+fn or() {
+    test_reg_reg_op!(1, or, 0xffffffff, 0xff00ff00, 0x00ff00ff);
+    test_reg_reg_op!(2, or, 0xffffffff, 0xffffffff, 0);
+}
+
+#[test]
+fn xor() {
+    test_reg_reg_op!(1, xor, 0xffffffff, 0xff00ff00, 0x00ff00ff);
+    test_reg_reg_op!(2, xor, 0, 0xffffffff, 0xffffffff);
+}
+
+#[test]
+fn store_then_load_round_trips() {
+    let mut cpu = Processor::<u32>::new();
+    let base: Register = 1;
+    cpu.set(base, 0x100);
+
+    cpu.set(2, 0xdeadbeef);
+    cpu.sw(base, 2, 0).unwrap();
+    cpu.lw(3, base, 0).unwrap();
+    assert_eq!(0xdeadbeef, cpu.get(3));
+
+    cpu.set(2, 0xff);
+    cpu.sb(base, 2, 4).unwrap();
+    cpu.lb(3, base, 4).unwrap();
+    assert_eq!(0xffffffff, cpu.get(3)); // sign-extended
+    cpu.lbu(3, base, 4).unwrap();
+    assert_eq!(0x000000ff, cpu.get(3)); // zero-extended
+
+    cpu.set(2, 0x8000);
+    cpu.sh(base, 2, 8).unwrap();
+    cpu.lh(3, base, 8).unwrap();
+    assert_eq!(0xffff8000, cpu.get(3)); // sign-extended
+    cpu.lhu(3, base, 8).unwrap();
+    assert_eq!(0x00008000, cpu.get(3)); // zero-extended
+}
+
+#[test]
+fn misaligned_access_is_trapped() {
+    let mut cpu = Processor::<u32>::new();
+    cpu.set(1, 1);
+    assert_eq!(Err(Trap::LoadAddressMisaligned(1)), cpu.lw(2, 1, 0));
+    assert_eq!(Err(Trap::StoreAddressMisaligned(1)), cpu.sh(1, 2, 0));
+}
+
+#[test]
+fn out_of_bounds_access_is_trapped() {
+    let mut cpu = Processor::<u32>::new();
+    let addr = MEMORY_SIZE as u32;
+    cpu.set(1, addr);
+    assert_eq!(Err(Trap::LoadAccessFault(addr)), cpu.lb(2, 1, 0));
+    assert_eq!(Err(Trap::StoreAccessFault(addr)), cpu.sb(1, 2, 0));
+}
+
+#[test]
+fn ecall_exit_halts_with_status_from_a0() {
+    // ECALL
+    let word = OPCODE_SYSTEM;
+    let mut cpu = Processor::<u32>::new();
+    cpu.set(REG_A7, SYS_EXIT);
+    cpu.set(REG_A0, 42);
+    assert_eq!(Ok(ExecOutcome::Halt(42)), cpu.execute(decode(word)));
+}
+
+#[test]
+fn ecall_shutdown_halts_with_status_zero() {
+    let word = OPCODE_SYSTEM;
+    let mut cpu = Processor::<u32>::new();
+    cpu.set(REG_A7, SYS_SHUTDOWN);
+    assert_eq!(Ok(ExecOutcome::Halt(0)), cpu.execute(decode(word)));
+}
+
+#[test]
+fn ebreak_reports_a_breakpoint() {
+    // EBREAK
+    let word = (1 << 20) | OPCODE_SYSTEM;
+    let mut cpu = Processor::<u32>::new();
+    assert_eq!(Ok(ExecOutcome::Breakpoint), cpu.execute(decode(word)));
+}
+
+#[test]
+fn run_stops_at_the_first_halting_ecall() {
+    // ADDI x10, x0, 7 ; ECALL (EXIT)
+    let addi_a0_7 = (7 << 20) | (0 << 15) | (0b000 << 12) | (REG_A0 << 7) as u32 | OPCODE_OP_IMM;
+    let addi_a7_exit =
+        (SYS_EXIT << 20) | (0 << 15) | (0b000 << 12) | (REG_A7 << 7) as u32 | OPCODE_OP_IMM;
+    let ecall = OPCODE_SYSTEM;
+    let program = [addi_a0_7, addi_a7_exit, ecall];
+
+    let mut cpu = Processor::<u32>::new();
+    assert_eq!(Ok(ExecOutcome::Halt(7)), cpu.run(&program));
+}
+
+#[test]
+fn rv64_widens_registers_and_shift_amounts() {
+    let mut cpu = Processor::<u64>::new();
+    let rd: Register = 1;
+    let rs1: Register = 3;
+
+    // ADDI sign-extends its immediate across all 64 bits, not just 32.
+    cpu.set(rs1, 0);
+    cpu.addi(rd, rs1, sign_extend(0x800));
+    assert_eq!(0xffff_ffff_ffff_f800, cpu.get(rd));
+
+    // Shift amounts are masked to 6 bits on RV64, so shamt=32 is significant.
+    cpu.set(rs1, 1);
+    cpu.slli(rd, rs1, 32);
+    assert_eq!(1u64 << 32, cpu.get(rd));
+}
+
+#[test]
+fn illegal_shift_encoding_is_trapped() {
+    // SLLI with funct7 = 0100000 (the SRAI encoding) is not a valid SLLI.
+    let word = (0b0100000 << 25) | (5 << 20) | (3 << 15) | (0b001 << 12) | (1 << 7) | OPCODE_OP_IMM;
+    let mut cpu = Processor::<u32>::new();
+    assert_eq!(
+        Err(Trap::IllegalInstruction(word)),
+        cpu.execute(decode(word))
+    );
+
+    // SRLI/SRAI with a funct7 that is neither 0000000 nor 0100000.
+    let word = (0b0000001 << 25) | (5 << 20) | (3 << 15) | (0b101 << 12) | (1 << 7) | OPCODE_OP_IMM;
+    let mut cpu = Processor::<u32>::new();
+    assert_eq!(
+        Err(Trap::IllegalInstruction(word)),
+        cpu.execute(decode(word))
+    );
+}